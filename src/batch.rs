@@ -1,6 +1,5 @@
 use std::{
     io::{self, SeekFrom},
-    os::unix::prelude::MetadataExt,
     path::{Path, PathBuf},
     pin::pin,
     task::Poll,
@@ -9,18 +8,22 @@ use std::{
 use futures::{future, Stream, StreamExt, TryStreamExt};
 use thiserror::Error;
 use tokio::{
-    fs::{File, OpenOptions},
-    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader},
+    fs::File,
+    io::{AsyncBufReadExt, AsyncSeekExt, BufReader},
 };
 use tokio_stream::wrappers::LinesStream;
 use vectorlink::openai::truncated_tokens_for;
 
 use crate::{
     openai::{embeddings_for, EmbeddingError},
+    progress_log::{ProgressLog, ProgressRecord},
     server::Operation,
     vecmath::Embedding,
+    vector_sink::{open_vector_sink, VectorSink, VectorSinkBackend},
 };
 
+const EMBEDDING_BYTES: u64 = std::mem::size_of::<Embedding>() as u64;
+
 #[derive(Error, Debug)]
 pub enum VectorizationError {
     #[error(transparent)]
@@ -29,50 +32,26 @@ pub enum VectorizationError {
     Io(#[from] io::Error),
 }
 
-async fn save_embeddings(
-    vec_file: &mut File,
-    offset: usize,
-    embeddings: &[Embedding],
-) -> Result<(), VectorizationError> {
-    let transmuted = unsafe {
-        std::slice::from_raw_parts(embeddings.as_ptr() as *const u8, embeddings.len() * 4)
-    };
-    vec_file.seek(SeekFrom::Start(offset as u64)).await?;
-    vec_file.write_all(transmuted).await?;
-    vec_file.flush().await?;
-    vec_file.sync_data().await?;
-
-    Ok(())
-}
-
 pub async fn vectorize_from_operations<
     S: Stream<Item = io::Result<Operation>>,
     P: AsRef<Path> + Unpin,
 >(
     api_key: &str,
-    vec_file: &mut File,
+    vec_sink: &mut dyn VectorSink,
+    vector_path: &Path,
     op_stream: S,
-    progress_file_path: P,
+    progress_log_path: P,
+    batch_policy: BatchPolicy,
 ) -> Result<usize, VectorizationError> {
-    let mut progress_file = OpenOptions::new()
-        .create(true)
-        .read(true)
-        .write(true)
-        .open(progress_file_path)
-        .await?;
-    let mut offset;
-    if progress_file.metadata().await?.size() != 8 {
-        // assume we have to start from scratch
-        progress_file.write_u64(0).await?;
-        offset = 0;
-    } else {
-        offset = progress_file.read_u64().await?;
-    }
+    let mut progress = ProgressLog::load(progress_log_path).await?;
+    let checkpoint = progress.verify(vector_path).await?;
+    let mut operations_consumed = checkpoint.operations_consumed;
+    let mut vector_offset = checkpoint.vector_offset;
 
     let filtered_op_stream = pin!(op_stream
         .try_filter_map(|o| future::ready(Ok(o.string())))
-        .skip(offset as usize));
-    let chunked_op_stream = TokenChunk::new(filtered_op_stream, 1_000_000);
+        .skip(operations_consumed as usize));
+    let chunked_op_stream = TokenChunk::new(filtered_op_stream, batch_policy);
     let mut taskstream = chunked_op_stream
         .map(|chunk| {
             let inner_api_key = api_key.to_string();
@@ -81,23 +60,30 @@ pub async fn vectorize_from_operations<
         .buffered(10);
 
     let mut failures = 0;
-    eprintln!("starting indexing at {offset}");
+    eprintln!("starting indexing at operation {operations_consumed}");
     while let Some(embeds) = taskstream.next().await {
         eprintln!("start of loop");
         let (embeddings, chunk_failures) = embeds.unwrap()?;
         eprintln!("retrieved embeddings");
 
-        save_embeddings(vec_file, offset as usize, &embeddings).await?;
+        vec_sink
+            .write_embeddings(vector_offset as usize, &embeddings)
+            .await?;
         eprintln!("saved embeddings");
         failures += chunk_failures;
-        offset += embeddings.len() as u64;
-        progress_file.seek(SeekFrom::Start(0)).await?;
-        progress_file.write_u64(offset).await?;
-        progress_file.flush().await?;
-        progress_file.sync_data().await?;
-        eprintln!("indexed {offset}");
+        operations_consumed += embeddings.len() as u64;
+        vector_offset += embeddings.len() as u64 * EMBEDDING_BYTES;
+        progress
+            .append(ProgressRecord {
+                operations_consumed,
+                vector_offset,
+            })
+            .await?;
+        eprintln!("indexed {operations_consumed} operations, {vector_offset} bytes");
     }
 
+    vec_sink.finish().await?;
+
     Ok(failures)
 }
 
@@ -128,6 +114,7 @@ pub async fn index_from_operations_file<P: AsRef<Path>>(
     op_file_path: P,
     vectorlink_path: P,
     domain: &str,
+    backend: VectorSinkBackend,
 ) -> Result<(), VectorizationError> {
     let mut staging_path: PathBuf = vectorlink_path.as_ref().into();
     staging_path.push(".staging");
@@ -136,53 +123,98 @@ pub async fn index_from_operations_file<P: AsRef<Path>>(
 
     let mut vector_path = staging_path.clone();
     vector_path.push("vectors");
-    let mut vec_file = OpenOptions::new()
-        .create(true)
-        .write(true)
-        .open(&vector_path)
-        .await?;
-    let mut progress_file_path = staging_path.clone();
-    progress_file_path.push("progress");
+    let mut vec_sink = open_vector_sink(&vector_path, backend).await?;
+    let mut progress_log_path = staging_path.clone();
+    progress_log_path.push("progress");
 
     let mut op_file = File::open(op_file_path).await?;
     let op_stream = get_operations_from_file(&mut op_file).await?;
 
-    vectorize_from_operations(api_key, &mut vec_file, op_stream, progress_file_path).await?;
+    vectorize_from_operations(
+        api_key,
+        vec_sink.as_mut(),
+        &vector_path,
+        op_stream,
+        progress_log_path,
+        BatchPolicy::TokenBudget(1_000_000),
+    )
+    .await?;
 
     Ok(())
 }
 
+/// A threshold policy governing when [`TokenChunk`] cuts a batch.
+#[derive(Debug, Clone, Copy)]
+pub enum BatchPolicy {
+    /// Flush once the accumulated token count (via `truncated_tokens_for`)
+    /// would exceed the given budget. This is the original behavior, tuned
+    /// to an embedding model's context limit.
+    TokenBudget(usize),
+    /// Flush once the given number of items has been collected.
+    ItemCount(usize),
+    /// Flush once the accumulated UTF-8 byte length would exceed the given
+    /// budget.
+    ByteBudget(usize),
+}
+
+impl BatchPolicy {
+    fn weigh(&self, s: &str) -> usize {
+        match self {
+            BatchPolicy::TokenBudget(_) => truncated_tokens_for(s).len(),
+            BatchPolicy::ItemCount(_) => 1,
+            BatchPolicy::ByteBudget(_) => s.len(),
+        }
+    }
+
+    fn limit(&self) -> usize {
+        match self {
+            BatchPolicy::TokenBudget(limit)
+            | BatchPolicy::ItemCount(limit)
+            | BatchPolicy::ByteBudget(limit) => *limit,
+        }
+    }
+}
+
 struct TokenChunk<S: Stream<Item = Result<String, E>> + Unpin, E> {
     stream: S,
-    limit: usize,
+    policy: BatchPolicy,
     collector: Vec<String>,
     current_count: usize,
 }
 
 impl<S: Stream<Item = Result<String, E>> + Unpin, E> TokenChunk<S, E> {
-    fn new(stream: S, limit: usize) -> Self {
+    fn new(stream: S, policy: BatchPolicy) -> Self {
         Self {
             stream,
-            limit,
+            policy,
             collector: Vec::new(),
             current_count: 0,
         }
     }
+
     fn collect_string(&mut self, s: String) -> Option<Vec<String>> {
-        let tokens = truncated_tokens_for(&s);
-        let new_count = self.current_count + tokens.len();
-        if new_count > self.limit {
-            self.current_count = tokens.len();
-            let mut new_vec = Vec::new();
-            new_vec.push(s);
+        let weight = self.policy.weigh(&s);
+        if !self.collector.is_empty() && self.current_count + weight > self.policy.limit() {
+            self.current_count = weight;
+            let mut new_vec = vec![s];
             std::mem::swap(&mut new_vec, &mut self.collector);
-            eprintln!("collected {} strings", new_vec.len());
             Some(new_vec)
         } else {
+            self.current_count += weight;
             self.collector.push(s);
             None
         }
     }
+
+    /// Take whatever has been buffered so far, resetting the accumulator.
+    fn take_tail(&mut self) -> Option<Vec<String>> {
+        if self.collector.is_empty() {
+            None
+        } else {
+            self.current_count = 0;
+            Some(std::mem::take(&mut self.collector))
+        }
+    }
 }
 
 impl<S: Stream<Item = Result<String, E>> + Unpin, E> Stream for TokenChunk<S, E> {
@@ -192,15 +224,86 @@ impl<S: Stream<Item = Result<String, E>> + Unpin, E> Stream for TokenChunk<S, E>
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        let next = self.stream.poll_next_unpin(cx);
-        match next {
-            Poll::Ready(Some(Ok(string))) => match self.collect_string(string) {
-                Some(result) => Poll::Ready(Some(Ok(result))),
-                None => Poll::Pending,
-            },
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-            Poll::Pending => Poll::Pending,
+        loop {
+            match self.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(string))) => {
+                    if let Some(batch) = self.collect_string(string) {
+                        return Poll::Ready(Some(Ok(batch)));
+                    }
+                    // Buffered without filling a batch: the inner stream
+                    // already made progress, so keep polling it instead of
+                    // returning `Pending` with nothing left to wake us.
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => {
+                    return match self.take_tail() {
+                        Some(tail) => Poll::Ready(Some(Ok(tail))),
+                        None => Poll::Ready(None),
+                    };
+                }
+                Poll::Pending => return Poll::Pending,
+            }
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::pin::Pin;
+    use std::task::Context;
+
+    use futures::task::noop_waker;
+
+    use super::*;
+
+    struct ScriptedStream {
+        script: VecDeque<Poll<Option<Result<String, io::Error>>>>,
+    }
+
+    impl Stream for ScriptedStream {
+        type Item = Result<String, io::Error>;
+
+        fn poll_next(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Self::Item>> {
+            self.script.pop_front().unwrap_or(Poll::Ready(None))
+        }
+    }
+
+    #[tokio::test]
+    async fn flushes_partial_tail_when_stream_ends() {
+        let stream = futures::stream::iter(vec![
+            Ok::<_, io::Error>("a".to_string()),
+            Ok("b".to_string()),
+        ]);
+        let mut chunk = TokenChunk::new(stream, BatchPolicy::ItemCount(10));
+
+        let batch = chunk.next().await.unwrap().unwrap();
+        assert_eq!(batch, vec!["a".to_string(), "b".to_string()]);
+        assert!(chunk.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn drains_every_ready_item_before_reporting_pending() {
+        let script = VecDeque::from(vec![
+            Poll::Ready(Some(Ok("a".to_string()))),
+            Poll::Ready(Some(Ok("b".to_string()))),
+            Poll::Pending,
+        ]);
+        let mut chunk = TokenChunk::new(
+            ScriptedStream { script },
+            BatchPolicy::ItemCount(10), // large enough to never cut a batch
+        );
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        // A single poll_next call must drain both buffered-but-not-flushed
+        // `Ready` items rather than bailing out to `Pending` after the
+        // first one with nothing scheduled to wake it again.
+        let polled = Pin::new(&mut chunk).poll_next(&mut cx);
+        assert!(matches!(polled, Poll::Pending));
+        assert_eq!(chunk.collector, vec!["a".to_string(), "b".to_string()]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,362 @@
+//! A compressed, block-indexed on-disk vector format: zstd-compressed
+//! fixed-size blocks of embeddings, plus a length/offset table for random
+//! access.
+
+use std::io::{self, SeekFrom};
+use std::path::Path;
+
+use async_trait::async_trait;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::batch::VectorizationError;
+use crate::vecmath::Embedding;
+use crate::vector_sink::VectorSink;
+
+/// Number of embeddings compressed together into a single block.
+pub const BLOCK_SIZE: usize = 256;
+
+const EMBEDDING_BYTES: usize = std::mem::size_of::<Embedding>();
+
+/// A compressed vector store: a sequence of zstd-compressed blocks of
+/// `BLOCK_SIZE` embeddings each, followed by a table recording the
+/// compressed byte length of every block.
+///
+/// The table is appended after the payload rather than kept as a header so
+/// that writers never have to rewrite earlier bytes: `append_block` only
+/// ever extends the file, and the table is written once, at `finish` time.
+pub struct CompressedVectorFile {
+    file: File,
+    level: i32,
+    block_offsets: Vec<u64>,
+    block_lengths: Vec<u32>,
+    next_offset: u64,
+    total: u64,
+}
+
+impl CompressedVectorFile {
+    /// Create a new, empty compressed vector file at `path`, compressing
+    /// blocks at the given zstd `level`, discarding anything already there.
+    pub async fn create<P: AsRef<Path>>(path: P, level: i32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(true)
+            .read(true)
+            .write(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file,
+            level,
+            block_offsets: Vec::new(),
+            block_lengths: Vec::new(),
+            next_offset: 0,
+            total: 0,
+        })
+    }
+
+    /// Open an existing compressed vector file (or create a new one) for
+    /// appending further blocks, reconstructing `block_offsets`,
+    /// `block_lengths` and the write position from the trailer left by a
+    /// previous `finish()`.
+    ///
+    /// The old trailer is truncated away so `append_block` can resume
+    /// writing payload bytes right where the last block ended; a fresh
+    /// trailer covering every block (old and new) is written by the next
+    /// `finish()` call.
+    pub async fn open_for_append<P: AsRef<Path>>(path: P, level: i32) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(path)
+            .await?;
+        let file_len = file.metadata().await?.len();
+
+        if file_len == 0 {
+            return Ok(Self {
+                file,
+                level,
+                block_offsets: Vec::new(),
+                block_lengths: Vec::new(),
+                next_offset: 0,
+                total: 0,
+            });
+        }
+
+        let mut file = file;
+        file.seek(SeekFrom::End(-8)).await?;
+        let total = file.read_u64().await?;
+        file.seek(SeekFrom::End(-16)).await?;
+        let block_count = file.read_u64().await? as usize;
+
+        let table_bytes = block_count as u64 * 4;
+        let table_start = file_len - 16 - table_bytes;
+        file.seek(SeekFrom::Start(table_start)).await?;
+        let mut block_lengths = Vec::with_capacity(block_count);
+        for _ in 0..block_count {
+            block_lengths.push(file.read_u32().await?);
+        }
+
+        let mut block_offsets = Vec::with_capacity(block_count);
+        let mut next_offset = 0u64;
+        for length in &block_lengths {
+            block_offsets.push(next_offset);
+            next_offset += *length as u64;
+        }
+
+        file.set_len(next_offset).await?;
+
+        Ok(Self {
+            file,
+            level,
+            block_offsets,
+            block_lengths,
+            next_offset,
+            total,
+        })
+    }
+
+    /// Compress `embeddings` as a single block, append it to the file, and
+    /// sync it to disk before returning.
+    ///
+    /// Callers are expected to batch embeddings up to [`BLOCK_SIZE`] before
+    /// calling this, but any non-empty slice is accepted so the final,
+    /// partially-filled block of a domain can be flushed as-is. Syncing here
+    /// (rather than only in `finish`) makes every appended block durable as
+    /// soon as it is written; embeddings that `CompressedVectorSink` still
+    /// holds in its pending buffer are not on disk until a block fills or
+    /// `finish` is called.
+    pub async fn append_block(&mut self, embeddings: &[Embedding]) -> io::Result<()> {
+        let raw = unsafe {
+            std::slice::from_raw_parts(
+                embeddings.as_ptr() as *const u8,
+                embeddings.len() * EMBEDDING_BYTES,
+            )
+        };
+        let compressed = zstd::encode_all(raw, self.level)?;
+
+        self.file.seek(SeekFrom::Start(self.next_offset)).await?;
+        self.file.write_all(&compressed).await?;
+        self.file.flush().await?;
+        self.file.sync_data().await?;
+
+        self.block_offsets.push(self.next_offset);
+        self.block_lengths.push(compressed.len() as u32);
+        self.next_offset += compressed.len() as u64;
+        self.total += embeddings.len() as u64;
+
+        Ok(())
+    }
+
+    /// Number of embeddings appended so far.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Write the offset table after the payload and flush it durably.
+    ///
+    /// Must be called after the last `append_block` and before the file is
+    /// read back with [`CompressedVectorFile::open`].
+    pub async fn finish(&mut self) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(self.next_offset)).await?;
+        for length in &self.block_lengths {
+            self.file.write_u32(*length).await?;
+        }
+        self.file.write_u64(self.block_lengths.len() as u64).await?;
+        self.file.write_u64(self.total).await?;
+        self.file.flush().await?;
+        self.file.sync_data().await?;
+
+        Ok(())
+    }
+
+    /// Open an existing compressed vector file for random-access reads,
+    /// reconstructing the offset table from its trailer.
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<CompressedVectorReader> {
+        let mut file = OpenOptions::new().read(true).open(path).await?;
+        let file_len = file.metadata().await?.len();
+
+        file.seek(SeekFrom::End(-8)).await?;
+        let total = file.read_u64().await?;
+        file.seek(SeekFrom::End(-16)).await?;
+        let block_count = file.read_u64().await? as usize;
+
+        let table_bytes = block_count as u64 * 4;
+        let table_start = file_len - 16 - table_bytes;
+        file.seek(SeekFrom::Start(table_start)).await?;
+        let mut block_lengths = Vec::with_capacity(block_count);
+        for _ in 0..block_count {
+            block_lengths.push(file.read_u32().await?);
+        }
+
+        let mut block_offsets = Vec::with_capacity(block_count);
+        let mut offset = 0u64;
+        for length in &block_lengths {
+            block_offsets.push(offset);
+            offset += *length as u64;
+        }
+
+        Ok(CompressedVectorReader {
+            file,
+            block_offsets,
+            block_lengths,
+            total,
+        })
+    }
+}
+
+/// Random-access reader over a [`CompressedVectorFile`].
+pub struct CompressedVectorReader {
+    file: File,
+    block_offsets: Vec<u64>,
+    block_lengths: Vec<u32>,
+    total: u64,
+}
+
+impl CompressedVectorReader {
+    /// Number of embeddings per block in this file.
+    pub fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    /// Number of blocks in this file.
+    pub fn block_count(&self) -> usize {
+        self.block_lengths.len()
+    }
+
+    /// Total number of embeddings stored in this file.
+    pub fn len(&self) -> usize {
+        self.total as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Decompress the block containing `index` and return the embedding at
+    /// that position within it.
+    pub async fn get(&mut self, index: usize) -> io::Result<Embedding> {
+        let block = index / BLOCK_SIZE;
+        let within_block = index % BLOCK_SIZE;
+
+        let offset = *self
+            .block_offsets
+            .get(block)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "index out of range"))?;
+        let length = self.block_lengths[block] as usize;
+
+        let mut compressed = vec![0u8; length];
+        self.file.seek(SeekFrom::Start(offset)).await?;
+        self.file.read_exact(&mut compressed).await?;
+
+        let decompressed = zstd::decode_all(compressed.as_slice())?;
+        let embeddings: &[Embedding] = unsafe {
+            std::slice::from_raw_parts(
+                decompressed.as_ptr() as *const Embedding,
+                decompressed.len() / EMBEDDING_BYTES,
+            )
+        };
+
+        Ok(embeddings[within_block])
+    }
+
+    /// Decompress every block in order and return the full, densely packed
+    /// set of embeddings.
+    ///
+    /// Unlike calling [`CompressedVectorReader::get`] in a loop, this visits
+    /// each block exactly once rather than re-decompressing it for every
+    /// embedding inside it, so it's the right way to read this file when the
+    /// caller needs most or all of it (e.g. scoring recall against the
+    /// whole domain).
+    pub async fn read_all(&mut self) -> io::Result<Vec<Embedding>> {
+        let mut out = Vec::with_capacity(self.total as usize);
+        for block in 0..self.block_lengths.len() {
+            let offset = self.block_offsets[block];
+            let length = self.block_lengths[block] as usize;
+
+            let mut compressed = vec![0u8; length];
+            self.file.seek(SeekFrom::Start(offset)).await?;
+            self.file.read_exact(&mut compressed).await?;
+
+            let decompressed = zstd::decode_all(compressed.as_slice())?;
+            let embeddings: &[Embedding] = unsafe {
+                std::slice::from_raw_parts(
+                    decompressed.as_ptr() as *const Embedding,
+                    decompressed.len() / EMBEDDING_BYTES,
+                )
+            };
+            out.extend_from_slice(embeddings);
+        }
+
+        Ok(out)
+    }
+}
+
+/// [`VectorSink`] backed by [`CompressedVectorFile`].
+///
+/// The compressed format addresses by block index rather than byte offset,
+/// so this sink only supports the strictly sequential, contiguous write
+/// pattern `vectorize_from_operations` already uses; the `offset` argument
+/// is used purely to check that pattern holds.
+pub struct CompressedVectorSink {
+    file: CompressedVectorFile,
+    pending: Vec<Embedding>,
+    written: usize,
+}
+
+impl CompressedVectorSink {
+    /// Open (or resume) the compressed vector file at `path`. Resuming
+    /// reconstructs `written` from the file's own trailer rather than
+    /// trusting the caller, so a mismatched `offset` on the first
+    /// `write_embeddings` call is caught rather than silently accepted.
+    pub async fn open<P: AsRef<Path>>(path: P, level: i32) -> io::Result<Self> {
+        let file = CompressedVectorFile::open_for_append(path, level).await?;
+        let written = file.total() as usize;
+        Ok(Self {
+            file,
+            pending: Vec::with_capacity(BLOCK_SIZE),
+            written,
+        })
+    }
+}
+
+#[async_trait]
+impl VectorSink for CompressedVectorSink {
+    async fn write_embeddings(
+        &mut self,
+        offset: usize,
+        embeddings: &[Embedding],
+    ) -> Result<(), VectorizationError> {
+        let expected = self.written * EMBEDDING_BYTES;
+        if offset != expected {
+            return Err(VectorizationError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "CompressedVectorSink requires contiguous, in-order writes: \
+                     expected offset {expected}, got {offset}"
+                ),
+            )));
+        }
+
+        self.pending.extend_from_slice(embeddings);
+        self.written += embeddings.len();
+
+        while self.pending.len() >= BLOCK_SIZE {
+            let block: Vec<Embedding> = self.pending.drain(..BLOCK_SIZE).collect();
+            self.file.append_block(&block).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> Result<(), VectorizationError> {
+        if !self.pending.is_empty() {
+            let block = std::mem::take(&mut self.pending);
+            self.file.append_block(&block).await?;
+        }
+        self.file.finish().await?;
+
+        Ok(())
+    }
+}
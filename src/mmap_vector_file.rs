@@ -0,0 +1,73 @@
+//! A memory-mapped, zero-copy random-access reader over the raw vectors
+//! file, for HNSW construction and recall evaluation.
+//!
+//! Recall evaluation (`vectorlink-worker/src/recall.rs`) reads through this.
+//! HNSW construction itself happens inside `index_domain`, which this crate
+//! doesn't contain the source for, so it can't be wired through here too —
+//! that traversal still goes through whatever `index_domain` already does
+//! internally.
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::vecmath::Embedding;
+
+const EMBEDDING_BYTES: usize = std::mem::size_of::<Embedding>();
+
+/// A read-only, memory-mapped view of a raw vectors file.
+pub struct MmapVectorFile {
+    mmap: Mmap,
+}
+
+impl MmapVectorFile {
+    /// Map the vectors file at `path`.
+    ///
+    /// This is async-friendly in the sense that opening the file and the
+    /// actual `mmap(2)` call are pushed onto the blocking threadpool rather
+    /// than run directly on the async task. Pages are not read in here: the
+    /// page faults of first touch happen later, in `get` and `slice`, on
+    /// whatever thread calls them.
+    pub async fn open<P: AsRef<Path> + Send + 'static>(path: P) -> io::Result<Self> {
+        tokio::task::spawn_blocking(move || {
+            let file = std::fs::File::open(path)?;
+            let mmap = unsafe { Mmap::map(&file)? };
+            Ok(Self { mmap })
+        })
+        .await
+        .expect("mmap task panicked")
+    }
+
+    /// Number of embeddings mapped by this file.
+    pub fn len(&self) -> usize {
+        self.mmap.len() / EMBEDDING_BYTES
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Zero-copy view of the embedding at `index`.
+    pub fn get(&self, index: usize) -> &Embedding {
+        assert!(index < self.len(), "embedding index out of range");
+        let embeddings = self.as_slice();
+        &embeddings[index]
+    }
+
+    /// Zero-copy view of a contiguous range of embeddings.
+    pub fn slice(&self, range: Range<usize>) -> &[Embedding] {
+        assert!(range.end <= self.len(), "embedding range out of range");
+        &self.as_slice()[range]
+    }
+
+    fn as_slice(&self) -> &[Embedding] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self.mmap.as_ptr() as *const Embedding,
+                self.mmap.len() / EMBEDDING_BYTES,
+            )
+        }
+    }
+}
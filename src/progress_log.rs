@@ -0,0 +1,107 @@
+//! A durable, append-only log of indexing checkpoints, used to resume
+//! `vectorize_from_operations` without trusting the vectors file to be
+//! exactly aligned with a bare progress counter.
+
+use std::io::{self, SeekFrom};
+use std::path::Path;
+
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// A committed checkpoint: how many operations had been consumed from the
+/// input stream, and the resulting byte offset in the vectors file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgressRecord {
+    pub operations_consumed: u64,
+    pub vector_offset: u64,
+}
+
+/// Size in bytes of one serialized [`ProgressRecord`]: two big-endian `u64`s.
+const RECORD_BYTES: u64 = 16;
+
+/// An append-only log of [`ProgressRecord`] checkpoints.
+pub struct ProgressLog {
+    file: File,
+    records: Vec<ProgressRecord>,
+}
+
+impl ProgressLog {
+    /// Open (creating if necessary) the progress log at `path`, replaying
+    /// any previously committed checkpoints. A torn trailing record (left
+    /// by a crash mid-append) is discarded rather than treated as an error.
+    pub async fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .write(true)
+            .open(path)
+            .await?;
+
+        let len = file.metadata().await?.len();
+        let record_count = len / RECORD_BYTES;
+        let whole_bytes = record_count * RECORD_BYTES;
+        if whole_bytes != len {
+            file.set_len(whole_bytes).await?;
+        }
+
+        file.seek(SeekFrom::Start(0)).await?;
+        let mut records = Vec::with_capacity(record_count as usize);
+        for _ in 0..record_count {
+            let operations_consumed = file.read_u64().await?;
+            let vector_offset = file.read_u64().await?;
+            records.push(ProgressRecord {
+                operations_consumed,
+                vector_offset,
+            });
+        }
+
+        Ok(Self { file, records })
+    }
+
+    /// The most recently committed checkpoint, or the zero checkpoint if
+    /// nothing has been committed yet.
+    pub fn last(&self) -> ProgressRecord {
+        self.records.last().copied().unwrap_or_default()
+    }
+
+    /// Durably append a new checkpoint to the log.
+    pub async fn append(&mut self, record: ProgressRecord) -> io::Result<()> {
+        self.file.seek(SeekFrom::End(0)).await?;
+        self.file.write_u64(record.operations_consumed).await?;
+        self.file.write_u64(record.vector_offset).await?;
+        self.file.flush().await?;
+        self.file.sync_data().await?;
+        self.records.push(record);
+
+        Ok(())
+    }
+
+    /// Validate the vectors file at `vector_path` against the last
+    /// committed checkpoint, truncating it back to that checkpoint's
+    /// `vector_offset` if a crash left a torn, partially-written tail.
+    /// Returns the checkpoint to resume from.
+    pub async fn verify<P: AsRef<Path>>(&self, vector_path: P) -> io::Result<ProgressRecord> {
+        let checkpoint = self.last();
+
+        let vector_file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(vector_path)
+            .await?;
+        let actual_len = vector_file.metadata().await?.len();
+        if actual_len > checkpoint.vector_offset {
+            vector_file.set_len(checkpoint.vector_offset).await?;
+        } else if actual_len < checkpoint.vector_offset {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "vectors file is shorter ({actual_len} bytes) than the last committed \
+                     checkpoint ({} bytes); refusing to resume over a gap",
+                    checkpoint.vector_offset
+                ),
+            ));
+        }
+
+        Ok(checkpoint)
+    }
+}
@@ -0,0 +1,212 @@
+//! Pluggable backends for durably writing embedding vectors.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+use crate::batch::VectorizationError;
+use crate::vecmath::Embedding;
+
+/// A destination for embedding vectors written at a byte offset.
+///
+/// A successful `write_embeddings` call should be durable before it
+/// returns; implementations are free to pipeline or batch the underlying
+/// I/O however they like as long as that guarantee holds. There are two
+/// exceptions. The `uring` backend below bounds how far a write can lag
+/// behind instead, and guarantees full durability only once `finish` has
+/// been called. The compressed backend writes only whole blocks of
+/// `BLOCK_SIZE` embeddings; up to `BLOCK_SIZE - 1` embeddings stay buffered
+/// in memory, not on disk, until a later call fills a block or `finish`
+/// flushes them.
+#[async_trait]
+pub trait VectorSink: Send {
+    async fn write_embeddings(
+        &mut self,
+        offset: usize,
+        embeddings: &[Embedding],
+    ) -> Result<(), VectorizationError>;
+
+    /// Flush any state buffered across `write_embeddings` calls. Called once
+    /// after the last write. Backends that are already durable per-call
+    /// don't need to override the default no-op.
+    async fn finish(&mut self) -> Result<(), VectorizationError> {
+        Ok(())
+    }
+}
+
+/// Which [`VectorSink`] implementation to use for a given indexing run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorSinkBackend {
+    /// `seek` + `write_all` + `flush` + `sync_data` on a plain tokio file.
+    Tokio,
+    /// zstd-compressed, block-indexed storage via `CompressedVectorFile`.
+    Compressed { zstd_level: i32 },
+    /// Positioned writes submitted through a `tokio_uring` runtime.
+    #[cfg(feature = "io-uring")]
+    Uring,
+}
+
+impl Default for VectorSinkBackend {
+    fn default() -> Self {
+        VectorSinkBackend::Tokio
+    }
+}
+
+/// Open the vectors file at `path` and wrap it in the sink selected by
+/// `backend`.
+///
+/// The `Uring` backend requires that the caller is already running inside a
+/// `tokio_uring::Runtime`; it will not work on a plain tokio runtime.
+pub async fn open_vector_sink<P: AsRef<Path>>(
+    path: P,
+    backend: VectorSinkBackend,
+) -> std::io::Result<Box<dyn VectorSink>> {
+    match backend {
+        VectorSinkBackend::Tokio => Ok(Box::new(TokioVectorSink::open(path).await?)),
+        VectorSinkBackend::Compressed { zstd_level } => Ok(Box::new(
+            crate::compressed_vector_file::CompressedVectorSink::open(path, zstd_level).await?,
+        )),
+        #[cfg(feature = "io-uring")]
+        VectorSinkBackend::Uring => Ok(Box::new(uring::UringVectorSink::open(path).await?)),
+    }
+}
+
+/// The original backend: `seek` + `write_all` + `flush` + `sync_data` on a
+/// plain `tokio::fs::File`.
+pub struct TokioVectorSink {
+    file: File,
+}
+
+impl TokioVectorSink {
+    pub async fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(path)
+            .await?;
+        Ok(Self { file })
+    }
+}
+
+#[async_trait]
+impl VectorSink for TokioVectorSink {
+    async fn write_embeddings(
+        &mut self,
+        offset: usize,
+        embeddings: &[Embedding],
+    ) -> Result<(), VectorizationError> {
+        let transmuted = unsafe {
+            std::slice::from_raw_parts(
+                embeddings.as_ptr() as *const u8,
+                embeddings.len() * std::mem::size_of::<Embedding>(),
+            )
+        };
+        self.file
+            .seek(std::io::SeekFrom::Start(offset as u64))
+            .await?;
+        self.file.write_all(transmuted).await?;
+        self.file.flush().await?;
+        self.file.sync_data().await?;
+
+        Ok(())
+    }
+}
+
+/// io_uring backed sink, feature-gated behind `io-uring`.
+///
+/// Writes are submitted as positioned `write_at` operations, so no explicit
+/// `seek` is needed, and several chunk writes may be outstanding against the
+/// same file descriptor at once: `write_embeddings` bounds how many by
+/// waiting on the oldest outstanding submission once `MAX_IN_FLIGHT` is
+/// reached, rather than waiting on every write before submitting the next.
+/// `finish` drains whatever is still outstanding.
+///
+/// This means a `ProgressLog` checkpoint can run up to `MAX_IN_FLIGHT`
+/// writes ahead of what's actually synced to disk mid-run; everything is
+/// guaranteed flushed by the time `finish` returns. Requires a
+/// `tokio_uring::Runtime`; spawning `index_from_operations_file` on a plain
+/// tokio runtime will panic.
+#[cfg(feature = "io-uring")]
+pub mod uring {
+    use std::collections::VecDeque;
+    use std::path::Path;
+
+    use async_trait::async_trait;
+    use tokio_uring::fs::{File, OpenOptions};
+
+    use super::VectorSink;
+    use crate::batch::VectorizationError;
+    use crate::vecmath::Embedding;
+
+    /// Maximum number of `write_at` submissions kept outstanding before we
+    /// start waiting on the oldest one.
+    const MAX_IN_FLIGHT: usize = 4;
+
+    pub struct UringVectorSink {
+        file: File,
+        in_flight: VecDeque<tokio_uring::JoinHandle<std::io::Result<()>>>,
+    }
+
+    impl UringVectorSink {
+        pub async fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+            let file = OpenOptions::new().create(true).write(true).open(path).await?;
+            Ok(Self {
+                file,
+                in_flight: VecDeque::with_capacity(MAX_IN_FLIGHT),
+            })
+        }
+
+        async fn wait_oldest(&mut self) -> Result<(), VectorizationError> {
+            if let Some(handle) = self.in_flight.pop_front() {
+                handle
+                    .await
+                    .map_err(|e| {
+                        VectorizationError::Io(std::io::Error::new(std::io::ErrorKind::Other, e))
+                    })??;
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl VectorSink for UringVectorSink {
+        async fn write_embeddings(
+            &mut self,
+            offset: usize,
+            embeddings: &[Embedding],
+        ) -> Result<(), VectorizationError> {
+            if self.in_flight.len() >= MAX_IN_FLIGHT {
+                self.wait_oldest().await?;
+            }
+
+            let transmuted = unsafe {
+                std::slice::from_raw_parts(
+                    embeddings.as_ptr() as *const u8,
+                    embeddings.len() * std::mem::size_of::<Embedding>(),
+                )
+            }
+            .to_vec();
+
+            let file = self.file.try_clone().await?;
+            let handle = tokio_uring::spawn(async move {
+                let (res, _buf) = file.write_at(transmuted, offset as u64).await;
+                res?;
+                file.sync_all().await
+            });
+            self.in_flight.push_back(handle);
+
+            Ok(())
+        }
+
+        /// Wait on every outstanding submission so nothing is left unsynced
+        /// once the caller is done writing.
+        async fn finish(&mut self) -> Result<(), VectorizationError> {
+            while !self.in_flight.is_empty() {
+                self.wait_oldest().await?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use tokio::task::block_in_place;
@@ -5,7 +7,14 @@ use vectorlink::batch::index_domain;
 use vectorlink::openai::Model;
 use vectorlink_task::task::{SyncTaskLiveness, TaskHandler, TaskLiveness};
 
-use parallel_hnsw::progress::{Interrupt, ProgressMonitor};
+use parallel_hnsw::progress::{Interrupt, ProgressMonitor, ProgressUpdate};
+
+use crate::recall::{evaluate_recall, RecallSample};
+
+/// Number of neighbors used when scoring recall.
+const RECALL_K: usize = 10;
+/// Number of queries sampled when scoring recall.
+const RECALL_SAMPLES: usize = 100;
 
 #[derive(Serialize, Deserialize)]
 pub struct BuildIndexRequest {
@@ -14,11 +23,16 @@ pub struct BuildIndexRequest {
     directory: String,
     model: Model,
     quantized: bool,
+    /// Store the domain's vectors compressed (`CompressedVectorFile`)
+    /// instead of raw. Read back the same way during recall evaluation.
+    compressed: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum BuildIndexProgress {
-    Generate {},
+    Vectorizing { done: usize, total: usize },
+    BuildingLayers { layer: usize, nodes: usize },
+    EvaluatingRecall { sampled: usize },
 }
 
 #[derive(Serialize, Deserialize)]
@@ -41,7 +55,7 @@ impl TaskHandler for VectorlinkTaskHandler {
     async fn initialize(
         _live: TaskLiveness<Self::Init, Self::Progress>,
     ) -> Result<Self::Progress, Self::Error> {
-        Ok(BuildIndexProgress::Generate {})
+        Ok(BuildIndexProgress::Vectorizing { done: 0, total: 0 })
     }
     async fn process(
         live: TaskLiveness<Self::Init, Self::Progress>,
@@ -50,38 +64,74 @@ impl TaskHandler for VectorlinkTaskHandler {
         let init = live.init().unwrap().unwrap();
         let _state = live.progress().unwrap().unwrap();
         let mut monitor = TaskMonitor(live.into_sync().unwrap());
-        block_in_place(|| {
+        let index = block_in_place(|| {
             index_domain(
                 &key,
                 init.model,
-                init.directory,
+                init.directory.clone(),
                 &init.domain,
                 &init.commit,
                 12345,
                 init.quantized,
+                init.compressed,
                 &mut monitor,
             )
         })
         .unwrap();
 
-        Ok(BuildIndexCompletion { recall: 0.5 })
+        let vectors_path = vectors_path(&init.directory, &init.domain);
+        let RecallSample { sampled, recall } = evaluate_recall(
+            &vectors_path,
+            init.compressed,
+            RECALL_K,
+            RECALL_SAMPLES,
+            |query, k| index.search(query, k),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+        monitor
+            .0
+            .set_progress(BuildIndexProgress::EvaluatingRecall { sampled })
+            .map_err(|e| e.to_string())?;
+
+        Ok(BuildIndexCompletion { recall })
     }
 }
 
+/// Where `index_domain` leaves the vectors file for a domain (raw or
+/// compressed, depending on `BuildIndexRequest::compressed`), mirroring the
+/// staging layout `vectorize_from_operations` writes into.
+fn vectors_path(directory: &str, domain: &str) -> PathBuf {
+    let mut path = PathBuf::from(directory);
+    path.push(".staging");
+    path.push(domain);
+    path.push("vectors");
+    path
+}
+
 struct TaskMonitor(SyncTaskLiveness<BuildIndexRequest, BuildIndexProgress>);
 
 impl ProgressMonitor for TaskMonitor {
     fn update(
         &mut self,
-        _update: parallel_hnsw::progress::ProgressUpdate,
+        update: ProgressUpdate,
     ) -> Result<(), parallel_hnsw::progress::Interrupt> {
-        let liveness = &mut self.0;
-        liveness
-            .set_progress(BuildIndexProgress::Generate {})
-            .map_err(|_| Interrupt)
+        let progress = match update {
+            ProgressUpdate::Vectorizing { done, total } => {
+                BuildIndexProgress::Vectorizing { done, total }
+            }
+            ProgressUpdate::Layer { layer, nodes } => {
+                BuildIndexProgress::BuildingLayers { layer, nodes }
+            }
+            ProgressUpdate::Recall { sampled } => {
+                BuildIndexProgress::EvaluatingRecall { sampled }
+            }
+        };
+
+        self.0.set_progress(progress).map_err(|_| Interrupt)
     }
 
     fn keep_alive(&mut self) -> Box<dyn std::any::Any> {
         Box::new(self.0.guarded_keepalive())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,118 @@
+//! Measuring recall against a brute-force nearest-neighbor baseline.
+//!
+//! `BuildIndexCompletion { recall }` used to be a hardcoded `0.5`. This
+//! computes it for real: sample a handful of query vectors out of the
+//! domain's own stored embeddings, rank the rest by brute-force distance to
+//! get the ground truth top-k, and compare that against whatever the
+//! caller's approximate index returns for the same query.
+
+use std::io;
+use std::path::Path;
+
+use vectorlink::compressed_vector_file::CompressedVectorFile;
+use vectorlink::mmap_vector_file::MmapVectorFile;
+use vectorlink::vecmath::Embedding;
+
+const EMBEDDING_BYTES: usize = std::mem::size_of::<Embedding>();
+
+/// Result of a recall evaluation run.
+pub struct RecallSample {
+    /// Number of queries actually sampled (clamped to the domain size).
+    pub sampled: usize,
+    /// Fraction of approximate top-k results that matched the brute-force
+    /// ground truth, averaged over all sampled queries.
+    pub recall: f32,
+}
+
+/// Sample `sample_count` queries, evenly spaced across the vectors stored
+/// at `vectors_path`, and measure `approx_search`'s recall@`k` against a
+/// brute-force baseline computed directly over the stored embeddings.
+///
+/// `compressed` must match how `vectors_path` was written (see
+/// `VectorSinkBackend`): `true` reads it back through
+/// `CompressedVectorFile`, `false` through the raw `MmapVectorFile`.
+pub async fn evaluate_recall(
+    vectors_path: &Path,
+    compressed: bool,
+    k: usize,
+    sample_count: usize,
+    mut approx_search: impl FnMut(&Embedding, usize) -> Vec<usize>,
+) -> io::Result<RecallSample> {
+    let vectors = load_vectors(vectors_path, compressed).await?;
+    let total = vectors.len();
+    if total == 0 {
+        return Ok(RecallSample {
+            sampled: 0,
+            recall: 1.0,
+        });
+    }
+
+    let sample_count = sample_count.min(total);
+    let stride = (total / sample_count).max(1);
+
+    let mut hits = 0usize;
+    let mut considered = 0usize;
+    for i in 0..sample_count {
+        let query_index = (i * stride) % total;
+        let query = vectors[query_index];
+
+        let mut ground_truth: Vec<usize> = (0..total).filter(|&j| j != query_index).collect();
+        ground_truth.sort_by(|&a, &b| {
+            squared_distance(&query, &vectors[a])
+                .partial_cmp(&squared_distance(&query, &vectors[b]))
+                .unwrap()
+        });
+        ground_truth.truncate(k);
+
+        // The query is itself one of the stored vectors, so an approximate
+        // search will almost certainly return it (distance 0) as a top hit.
+        // It can never appear in `ground_truth`, so drop it here too rather
+        // than let it silently eat one of the `k` slots.
+        let approx: Vec<usize> = approx_search(&query, k + 1)
+            .into_iter()
+            .filter(|&idx| idx != query_index)
+            .take(k)
+            .collect();
+        hits += approx.iter().filter(|idx| ground_truth.contains(idx)).count();
+        considered += ground_truth.len();
+    }
+
+    let recall = if considered == 0 {
+        1.0
+    } else {
+        hits as f32 / considered as f32
+    };
+
+    Ok(RecallSample {
+        sampled: sample_count,
+        recall,
+    })
+}
+
+/// Read every embedding for a domain into memory through whichever backend
+/// wrote it.
+///
+/// Recall scoring touches most of the domain per sampled query (it ranks
+/// every other stored vector by distance), so reading the whole thing up
+/// front once is simpler than repeated random access — especially for the
+/// compressed backend, where random access means re-decompressing a block
+/// per lookup.
+async fn load_vectors(vectors_path: &Path, compressed: bool) -> io::Result<Vec<Embedding>> {
+    if compressed {
+        let mut reader = CompressedVectorFile::open(vectors_path).await?;
+        reader.read_all().await
+    } else {
+        let mapped = MmapVectorFile::open(vectors_path.to_path_buf()).await?;
+        Ok(mapped.slice(0..mapped.len()).to_vec())
+    }
+}
+
+fn squared_distance(a: &Embedding, b: &Embedding) -> f32 {
+    let a = unsafe {
+        std::slice::from_raw_parts(a as *const Embedding as *const f32, EMBEDDING_BYTES / 4)
+    };
+    let b = unsafe {
+        std::slice::from_raw_parts(b as *const Embedding as *const f32, EMBEDDING_BYTES / 4)
+    };
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}